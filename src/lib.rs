@@ -0,0 +1,865 @@
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CStr, CString};
+use std::path::{Component, Path};
+
+use anyhow::{bail, Result};
+#[cfg(target_os = "illumos")]
+use libc::c_char;
+use libc::{major, minor, stat, statvfs};
+
+mod mount_source;
+
+pub use mount_source::MountSource;
+
+#[allow(unused)]
+#[derive(Debug, Clone)]
+pub struct MntTabEnt {
+    pub special: String,
+    pub mountp: String,
+    pub fstype: String,
+    pub mntopts: String,
+    pub time: String,
+    pub major: u32,
+    pub minor: u32,
+    pub opts: MountOptions,
+}
+
+impl MntTabEnt {
+    pub fn getopt(&self, name: &str) -> Option<String> {
+        self.mntopts
+            .split(',')
+            .filter_map(|t| {
+                if let Some((k, v)) = t.split_once('=') {
+                    if k == name {
+                        Some(v.to_string())
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+            })
+            .next()
+    }
+}
+
+/// A parsed `mnt_mntopts` string: `key=value` pairs, like the "dev" and
+/// "zone" options, plus bare boolean flags, like "ro" and "nosuid".  This is
+/// parsed once per mount entry so that callers don't need to repeatedly
+/// split and scan the raw comma-separated option string themselves.
+#[derive(Debug, Clone, Default)]
+pub struct MountOptions {
+    values: HashMap<String, String>,
+    flags: HashSet<String>,
+}
+
+impl MountOptions {
+    pub fn parse(raw: &str) -> MountOptions {
+        let mut values = HashMap::new();
+        let mut flags = HashSet::new();
+
+        for tok in raw.split(',') {
+            if tok.is_empty() {
+                continue;
+            }
+
+            if let Some((k, v)) = tok.split_once('=') {
+                values.insert(k.to_string(), v.to_string());
+            } else {
+                flags.insert(tok.to_string());
+            }
+        }
+
+        MountOptions { values, flags }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    pub fn has_flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+
+    pub fn is_readonly(&self) -> bool {
+        self.has_flag("ro")
+    }
+
+    pub fn is_readwrite(&self) -> bool {
+        self.has_flag("rw")
+    }
+
+    pub fn nosuid(&self) -> bool {
+        self.has_flag("nosuid")
+    }
+
+    pub fn noexec(&self) -> bool {
+        self.has_flag("noexec")
+    }
+
+    pub fn noatime(&self) -> bool {
+        self.has_flag("noatime")
+    }
+
+    pub fn nodevices(&self) -> bool {
+        self.has_flag("nodevices")
+    }
+
+    pub fn setuid(&self) -> bool {
+        self.has_flag("setuid")
+    }
+
+    pub fn devices(&self) -> bool {
+        self.has_flag("devices")
+    }
+
+    /// Whether extended attributes are enabled, if the mount entry expressed
+    /// an opinion either way.
+    pub fn xattr(&self) -> Option<bool> {
+        if self.has_flag("xattr") {
+            Some(true)
+        } else if self.has_flag("noxattr") {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// The name of the zone that owns this mount, if the "zone" option was
+    /// present.
+    pub fn zone(&self) -> Option<&str> {
+        self.get("zone")
+    }
+
+    /// The "dev" option, a hex-formatted compressed device ID, parsed into a
+    /// real integer for numeric comparison.
+    pub fn dev(&self) -> Option<u64> {
+        u64::from_str_radix(self.get("dev")?, 16).ok()
+    }
+}
+
+/// The result of classifying a path against the mounted ZFS file systems: is
+/// it the live dataset itself, or a path reached through a snapshot?
+///
+/// For `Snapshot`, `dataset` is `dataset@snapshot` when the snapshot name
+/// could be recovered from the path (see [`classify_path()`]), or just the
+/// backing dataset name otherwise -- in which case `dev` is the only thing
+/// that distinguishes this snapshot from others of the same dataset.
+///
+/// In both cases `capacity` is derived from the same statvfs(2) call used to
+/// classify the path, and so always describes the live dataset -- even for
+/// a `Snapshot`, since statvfs(2) reports the live file system regardless of
+/// which side of the snapdir the path in question was on.
+#[derive(Debug, Clone)]
+pub enum PathClass {
+    Live { dataset: String, fsid: u64, dev: u64, capacity: Capacity },
+    Snapshot { dataset: String, fsid: u64, dev: u64, capacity: Capacity },
+}
+
+/// Disk usage figures for a file system, derived from statvfs(2) block and
+/// inode counts.  All byte figures are in bytes, not blocks.
+#[derive(Debug, Clone, Copy)]
+pub struct Capacity {
+    pub total: u64,
+    pub available: u64,
+    pub used: u64,
+    pub files: u64,
+    pub files_available: u64,
+}
+
+impl Capacity {
+    /*
+     * f_frsize/f_blocks/f_bfree/f_bavail/f_files/f_favail are c_ulong /
+     * fsblkcnt_t / fsfilcnt_t, which happen to already be u64 on illumos and
+     * on 64-bit Linux -- the casts below are a no-op there, but are kept so
+     * this still widens correctly if it's ever built for a 32-bit target
+     * where those types are narrower.
+     */
+    #[allow(clippy::unnecessary_cast)]
+    fn from_statvfs(f: &statvfs) -> Capacity {
+        let frsize = f.f_frsize as u64;
+        let blocks = f.f_blocks as u64;
+        let bfree = f.f_bfree as u64;
+        let bavail = f.f_bavail as u64;
+
+        Capacity {
+            total: blocks.saturating_mul(frsize),
+            available: bavail.saturating_mul(frsize),
+            used: blocks.saturating_sub(bfree).saturating_mul(frsize),
+            files: f.f_files as u64,
+            files_available: f.f_favail as u64,
+        }
+    }
+}
+
+/// Walk the (canonicalized) components of a path looking for the sequence
+/// ".zfs", "snapshot", "<name>" that identifies a path reached through the
+/// automounted snapdir; see zfsctl_snapdir_lookup().  The snapdir automount
+/// produces no visible mnttab entry, so this path walk is the only reliable
+/// way to recover the snapshot name -- the rest of the classification logic
+/// only gets us as far as the backing dataset.
+///
+/// Only the sequence where ".zfs" directly precedes "snapshot" is accepted,
+/// since a dataset mountpoint can itself legitimately contain a directory
+/// literally named "snapshot", and the ".zfs" directory may appear several
+/// levels above the file in question.
+fn snapshot_name_from_path(path: &Path) -> Option<String> {
+    let canon = std::fs::canonicalize(path).ok()?;
+
+    snapshot_name_from_components(canon.components())
+}
+
+fn snapshot_name_from_components<'a, I>(components: I) -> Option<String>
+where
+    I: Iterator<Item = Component<'a>>,
+{
+    let comps: Vec<Component> = components.collect();
+
+    for w in comps.windows(3) {
+        if w[0].as_os_str() == ".zfs" && w[1].as_os_str() == "snapshot" {
+            return Some(w[2].as_os_str().to_str()?.to_string());
+        }
+    }
+
+    None
+}
+
+pub(crate) fn last_errno() -> String {
+    std::io::Error::last_os_error().to_string()
+}
+
+/// Read every entry in the running system's mount table, using whichever
+/// [`MountSource`] backend is appropriate for the platform this was built
+/// for.  Callers that want to classify many paths should fetch this once
+/// and reuse it, rather than re-reading the mount table for each path.
+pub fn mount_table() -> Result<Vec<MntTabEnt>> {
+    mount_source::default_source().mounts()
+}
+
+/// True if `mountp` is `query_path` itself or one of its containing
+/// directories, in the `Path` component sense rather than naive string
+/// prefixing -- so a dataset mounted at `/` (e.g. a ZFS boot environment's
+/// root) correctly contains every path on the system, not just paths that
+/// happen to start with the literal string `"//"`.
+fn mountpoint_contains(mountp: &str, query_path: &str) -> bool {
+    Path::new(query_path).starts_with(mountp)
+}
+
+/// Rank a mount entry by how well its mountpoint matches `query_path`:
+/// entries whose mountpoint is a prefix of (or exactly) the path outrank
+/// ones that aren't, and among those, the longest mountpoint wins; among
+/// non-matching entries, the shortest mountpoint wins.  Used to pick a
+/// single representative out of several mount entries that otherwise tie.
+fn mount_rank(ent: &MntTabEnt, query_path: &str) -> (i32, i64) {
+    let is_prefix = mountpoint_contains(&ent.mountp, query_path);
+
+    if is_prefix {
+        (1, ent.mountp.len() as i64)
+    } else {
+        (0, -(ent.mountp.len() as i64))
+    }
+}
+
+/// Resolve a set of per-device mount-entry representatives down to a single
+/// match: the one whose mountpoint best matches `query_path`, per
+/// [`mount_rank`].  A representative for a containing dataset mounted
+/// further up the tree (e.g. "pool" vs. "pool/fs") simply ranks lower and
+/// loses -- that's an ordinary nested mount, not an ambiguity.  Only a
+/// genuine tie in rank between entries backed by different `special`
+/// devices is treated as one.
+fn resolve_reps<'a>(reps: Vec<&'a MntTabEnt>, query_path: &str) -> Result<&'a MntTabEnt> {
+    let best_rank = reps
+        .iter()
+        .map(|e| mount_rank(e, query_path))
+        .max()
+        .ok_or_else(|| anyhow::anyhow!("no match found?"))?;
+
+    let best: Vec<&MntTabEnt> = reps
+        .into_iter()
+        .filter(|e| mount_rank(e, query_path) == best_rank)
+        .collect();
+
+    let first = best[0];
+    if let Some(other) = best.iter().find(|e| e.special != first.special) {
+        bail!("two matches? {first:?} and {other:?}");
+    }
+
+    Ok(first)
+}
+
+/// A single ZFS file system can legitimately appear more than once in the
+/// mount table -- bind-style remounts, lofs overlays, or an NFS reexport
+/// pointing at the same underlying device all produce duplicate entries
+/// with the same `(major, minor)`.  Group `cands` by device and collapse
+/// each group down to one representative -- the entry whose mountpoint
+/// best matches `query_path`, per [`mount_rank`] -- the way `df`(1) filters
+/// its own mount list, before treating multiple survivors as a true
+/// ambiguity.  Two entries sharing a device but backed by different
+/// `special`s are not a duplicate, though, so that's checked and rejected
+/// before a representative is picked.
+///
+/// Used by both [`select_candidate`] (illumos) and [`find_containing_mount`]
+/// (the portable fallback), which otherwise differ only in how `cands` gets
+/// filtered down from the full mount table.
+fn dedupe_candidates_by_device<'a>(
+    cands: Vec<&'a MntTabEnt>,
+    query_path: &str,
+) -> Result<Vec<&'a MntTabEnt>> {
+    let mut by_dev: HashMap<(u32, u32), Vec<&'a MntTabEnt>> = HashMap::new();
+    for c in cands {
+        by_dev.entry((c.major, c.minor)).or_default().push(c);
+    }
+
+    let mut reps: Vec<&MntTabEnt> = Vec::new();
+    for group in by_dev.into_values() {
+        /*
+         * Two entries can share a device yet back genuinely different
+         * datasets; grouping by device alone must not paper over that, so
+         * check for it before picking a representative out of the group.
+         */
+        let first = group[0];
+        if let Some(other) = group.iter().find(|e| e.special != first.special) {
+            bail!("two matches? {first:?} and {other:?}");
+        }
+
+        /*
+         * Among entries sharing both a device and a `special`, prefer the
+         * one whose mountpoint best matches the queried path.
+         */
+        let rep = group
+            .into_iter()
+            .max_by_key(|e| mount_rank(e, query_path))
+            .unwrap();
+
+        reps.push(rep);
+    }
+
+    Ok(reps)
+}
+
+/// Resolve the illumos candidate set down to a single mount entry, per
+/// [`dedupe_candidates_by_device`] and [`resolve_reps`].
+#[cfg(target_os = "illumos")]
+fn select_candidate<'a>(
+    cands: Vec<&'a MntTabEnt>,
+    query_path: &str,
+) -> Result<&'a MntTabEnt> {
+    resolve_reps(dedupe_candidates_by_device(cands, query_path)?, query_path)
+}
+
+/// The illumos matching strategy: `statvfs(2)`'s `f_basetype` and `stat(2)`'s
+/// (otherwise-unexposed) `st_fstype` are both illumos-specific, as is the
+/// "dev" mnttab option used below to tie a mount entry back to the fsid
+/// `statvfs(2)` returned.  None of this is available on other platforms;
+/// see the portable fallback below for Linux and BSD.
+#[cfg(target_os = "illumos")]
+fn classify_against(
+    ents: &[MntTabEnt],
+    path: &CStr,
+) -> Result<PathClass> {
+    /*
+     * Perform a statvfs(2) call against the path.  Though we are providing
+     * a path that is potentially not the mount point of any particular file
+     * system, the call will determine which file system the file resides
+     * in.
+     *
+     * Even though we have nominated a file, this call is specific to the
+     * file system.  The process of automatically mounting snapshots under
+     * ".zfs/snapshot/NAME/..." is somewhat magical and does not result in a
+     * visible mount entry for the snapshot; see zfsctl_snapdir_lookup().
+     * Some of this magic is in service of NFS exports of ZFS file systems,
+     * to make the snapshot appear as effectively just a regular directory
+     * and thus not require an explicit and separate NFS mount on the client
+     * to cross into the snapdir.  Nonetheless, this also serves our
+     * purposes here.
+     *
+     * This call will end up telling us details about the live file system,
+     * even if we hit a snapshot.
+     */
+    let mut f: statvfs = unsafe { std::mem::zeroed() };
+    let res = unsafe { statvfs(path.as_ptr(), &mut f) };
+    if res != 0 {
+        bail!("statvfs({path:?}) failed: {}", last_errno());
+    }
+
+    /*
+     * Get the f_basetype string ready for comparison:
+     */
+    let basetype = unsafe { CStr::from_ptr(f.f_basetype.as_ptr()) };
+    let basetype = basetype.to_str().unwrap();
+
+    /*
+     * The fsid value is, today, the 32-bit compressed version of the unique
+     * device ID that ZFS created for the file system in question.  These
+     * IDs are ephemeral for the current import of the ZFS pool in question,
+     * but can be used to distinguish one dataset or snapshot from another.
+     *
+     * Unfortunately because it is a compressed device ID and this is a
+     * 64-bit system, I do not believe there is any public function that
+     * allows us to expand back to a native width device ID.  Fortunately,
+     * the "dev" mount option is _also_ expressed as a compressed device, so
+     * once parsed from hex it can be compared numerically with this number.
+     */
+    let fsid = f.f_fsid as u64;
+    let capacity = Capacity::from_statvfs(&f);
+
+    /*
+     * Now, make a stat(2) call against the path.  This call _is_
+     * vnode-specific, and thus some of the information we get will be
+     * lifted from the snapshot if this is one.
+     */
+    let mut st: stat = unsafe { std::mem::zeroed() };
+    let res = unsafe { stat(path.as_ptr(), &mut st) };
+    if res != 0 {
+        bail!("stat({path:?}) failed: {}", last_errno());
+    }
+
+    /*
+     * The device number is, again, ephemeral to this import but unique on
+     * the system at any given moment.  Snapshots get their own device
+     * numbers, which are visible through stat(2).  See
+     * zfs_create_unique_device().
+     */
+    let dev = st.st_dev as u64;
+
+    let fs_major = unsafe { major(st.st_dev) };
+    let fs_minor = unsafe { minor(st.st_dev) };
+
+    /*
+     * Get the st_fstype string ready for comparison and confirm it matches
+     * what we got from statvfs(2).
+     *
+     * The libc crate has made a curious decision to make "st_fstype" into a
+     * _private_ field named __unused.  It's hard to understand why such a
+     * hostile situation has arisen, but in the mean time it is in fact our
+     * computer:
+     */
+    let fstypeaddr =
+        ((std::ptr::addr_of!(st) as usize) + 0x70) as *const c_char;
+
+    let st_fstype = unsafe { CStr::from_ptr(fstypeaddr) };
+    let st_fstype = st_fstype.to_str().unwrap();
+
+    if st_fstype != basetype {
+        bail!("st_fstype {st_fstype:?} != f_basetype {basetype:?}");
+    }
+
+    /*
+     * Look for mnttab entries that match.
+     */
+    let cands: Vec<&MntTabEnt> = ents
+        .iter()
+        .filter(|ent| {
+            /*
+             * We need to make sure the file system base type (a name, like
+             * "zfs") matches the values we read earlier.  We also want to
+             * confirm, then, that the major number for the device is the
+             * same as the one we saw before.  For ZFS, this major number is
+             * the same for all pools, and does not reflect any underlying
+             * block storage device drivers.
+             */
+            if ent.fstype != basetype || fs_major != ent.major {
+                return false;
+            }
+
+            /*
+             * Does this mount entry match the file system device ID we got?
+             * This ID is for the live file system, whether or not we were
+             * looking at a snapshot, so it must match something visible in
+             * the mount table.
+             */
+            ent.opts.dev() == Some(fsid)
+        })
+        .collect();
+
+    let query_path = path.to_str().unwrap_or_default();
+    let rep = select_candidate(cands, query_path)?;
+
+    if fs_minor != rep.minor {
+        /*
+         * If the file system device minor number from stat(2) does not
+         * match the mount entry, but the statvfs(2) fsid does, this is a
+         * snapshot of that file system.
+         */
+        Ok(PathClass::Snapshot {
+            dataset: rep.special.to_string(),
+            fsid,
+            dev,
+            capacity,
+        })
+    } else {
+        /*
+         * Otherwise, if everything matches, this is the live file system
+         * itself.
+         */
+        Ok(PathClass::Live {
+            dataset: rep.special.to_string(),
+            fsid,
+            dev,
+            capacity,
+        })
+    }
+}
+
+/// The portable matching strategy used on Linux and BSD, where there is no
+/// `f_basetype`/`st_fstype` string available from `statvfs(2)`/`stat(2)`,
+/// and no "dev" mnttab option to tie a mount entry back to a fsid.  Instead,
+/// find the zfs mount entry whose mountpoint is the containing mount of the
+/// path -- the same algorithm `df`(1) uses to resolve a path to a mount --
+/// and compare its `(major, minor)`, which both the [`LinuxMountSource`] and
+/// [`BsdMountSource`] backends already derive via `stat(2)` on the
+/// mountpoint itself, against the `(major, minor)` of the path in question.
+///
+/// [`LinuxMountSource`]: crate::mount_source::LinuxMountSource
+/// [`BsdMountSource`]: crate::mount_source::BsdMountSource
+#[cfg(not(target_os = "illumos"))]
+fn classify_against(
+    ents: &[MntTabEnt],
+    path: &CStr,
+) -> Result<PathClass> {
+    /*
+     * This call will end up telling us details about the live file system,
+     * even if we hit a snapshot; see the longer explanation in the illumos
+     * implementation of this function.
+     */
+    let mut f: statvfs = unsafe { std::mem::zeroed() };
+    let res = unsafe { statvfs(path.as_ptr(), &mut f) };
+    if res != 0 {
+        bail!("statvfs({path:?}) failed: {}", last_errno());
+    }
+
+    let fsid = f.f_fsid as u64;
+    let capacity = Capacity::from_statvfs(&f);
+
+    let mut st: stat = unsafe { std::mem::zeroed() };
+    let res = unsafe { stat(path.as_ptr(), &mut st) };
+    if res != 0 {
+        bail!("stat({path:?}) failed: {}", last_errno());
+    }
+
+    let dev = st.st_dev as u64;
+    let fs_major = major(st.st_dev);
+    let fs_minor = minor(st.st_dev);
+
+    let query_path = path.to_str()?;
+
+    let ent = find_containing_mount(ents, query_path)?;
+
+    if (fs_major, fs_minor) != (ent.major, ent.minor) {
+        /*
+         * If the device of the path itself doesn't match the containing
+         * mount's device, this is taken to be a snapshot of that file
+         * system.  The statvfs(2) fsid is not compared against the mount
+         * entry here; it is only reported.
+         */
+        Ok(PathClass::Snapshot {
+            dataset: ent.special.to_string(),
+            fsid,
+            dev,
+            capacity,
+        })
+    } else {
+        /*
+         * Otherwise, if everything matches, this is the live file system
+         * itself.
+         */
+        Ok(PathClass::Live {
+            dataset: ent.special.to_string(),
+            fsid,
+            dev,
+            capacity,
+        })
+    }
+}
+
+/// Find the zfs mount entry that contains `query_path`: the one whose
+/// mountpoint is the longest prefix of (or exactly) the path, the same
+/// algorithm used to resolve a path to its containing mount in tools like
+/// `df`(1).
+///
+/// Like [`select_candidate`] on illumos, candidates are resolved via
+/// [`dedupe_candidates_by_device`] and [`resolve_reps`]: grouped by
+/// `(major, minor)` and collapsed to one representative per device, so
+/// bind-style remounts or overlays of the same dataset don't look like an
+/// ambiguity; a nested dataset mounted further up the tree just loses on
+/// prefix length, and only a genuine tie between entries backed by
+/// different `special` devices is an error.
+#[cfg(not(target_os = "illumos"))]
+fn find_containing_mount<'a>(
+    ents: &'a [MntTabEnt],
+    query_path: &str,
+) -> Result<&'a MntTabEnt> {
+    let cands: Vec<&MntTabEnt> = ents
+        .iter()
+        .filter(|ent| ent.fstype == "zfs")
+        .filter(|ent| mountpoint_contains(&ent.mountp, query_path))
+        .collect();
+
+    resolve_reps(dedupe_candidates_by_device(cands, query_path)?, query_path)
+}
+
+/// Classify a path as either living on the dataset directly, or being
+/// reached through a ZFS snapshot, by combining statvfs(2), stat(2), and the
+/// system mount table.
+///
+/// This fetches the mount table fresh on every call.  Callers that want to
+/// classify many paths in one go should call [`mount_table()`] once and pass
+/// that result to [`classify_path_in()`] rather than calling this function
+/// repeatedly.
+pub fn classify_path(path: &Path) -> Result<PathClass> {
+    classify_path_in(&mount_table()?, path)
+}
+
+/// Like [`classify_path()`], but against a mount table the caller already
+/// fetched with [`mount_table()`].
+pub fn classify_path_in(ents: &[MntTabEnt], path: &Path) -> Result<PathClass> {
+    let cpath = CString::new(path.as_os_str().as_encoded_bytes())?;
+
+    match classify_against(ents, &cpath)? {
+        PathClass::Snapshot { dataset, fsid, dev, capacity } => {
+            /*
+             * Try to recover the actual snapshot name from the path itself;
+             * if we can't (e.g. an NFS reexport of the snapdir, with no
+             * ".zfs/snapshot" segment visible locally), fall back to
+             * reporting just the backing dataset and the ephemeral st_dev
+             * minor.
+             */
+            let dataset = match snapshot_name_from_path(path) {
+                Some(name) => format!("{dataset}@{name}"),
+                None => dataset,
+            };
+
+            Ok(PathClass::Snapshot { dataset, fsid, dev, capacity })
+        }
+        other => Ok(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ent(special: &str, mountp: &str, major: u32, minor: u32, dev: &str) -> MntTabEnt {
+        let mntopts = format!("rw,dev={dev}");
+        let opts = MountOptions::parse(&mntopts);
+
+        MntTabEnt {
+            special: special.to_string(),
+            mountp: mountp.to_string(),
+            fstype: "zfs".to_string(),
+            mntopts,
+            time: "0".to_string(),
+            major,
+            minor,
+            opts,
+        }
+    }
+
+    #[test]
+    fn getopt_finds_key() {
+        let e = ent("pool/fs", "/pool/fs", 1, 2, "abc123");
+        assert_eq!(e.getopt("dev"), Some("abc123".to_string()));
+        assert_eq!(e.getopt("nosuchopt"), None);
+    }
+
+    #[test]
+    fn getopt_missing_key() {
+        let e = ent("pool/fs", "/pool/fs", 1, 2, "abc123");
+        assert_eq!(e.getopt("ro"), None);
+    }
+
+    #[test]
+    fn mount_options_parses_flags_and_values() {
+        let o = MountOptions::parse("ro,nosuid,dev=7a1,zone=global,xattr");
+        assert!(o.is_readonly());
+        assert!(!o.is_readwrite());
+        assert!(o.nosuid());
+        assert!(!o.noexec());
+        assert_eq!(o.dev(), Some(0x7a1));
+        assert_eq!(o.zone(), Some("global"));
+        assert_eq!(o.xattr(), Some(true));
+    }
+
+    #[test]
+    #[cfg(target_os = "illumos")]
+    fn select_candidate_collapses_duplicate_device() {
+        /*
+         * A bind-style remount or lofs overlay can put the same dataset in
+         * the mount table twice under the same device.
+         */
+        let a = ent("pool/fs", "/pool/fs", 1, 2, "abc");
+        let b = ent("pool/fs", "/pool/fs-bind", 1, 2, "abc");
+
+        let rep = select_candidate(vec![&a, &b], "/pool/fs/some/file").unwrap();
+        assert_eq!(rep.mountp, "/pool/fs");
+    }
+
+    #[test]
+    #[cfg(target_os = "illumos")]
+    fn select_candidate_prefers_longest_matching_prefix() {
+        /*
+         * The same dataset, bind-mounted or overlaid at two mountpoints;
+         * the one that actually contains the queried path should win.
+         */
+        let direct = ent("pool/fs", "/pool/fs", 1, 2, "abc");
+        let bound = ent("pool/fs", "/mnt/elsewhere", 1, 2, "abc");
+
+        let rep = select_candidate(vec![&direct, &bound], "/pool/fs/some/file").unwrap();
+        assert_eq!(rep.mountp, "/pool/fs");
+    }
+
+    #[test]
+    #[cfg(target_os = "illumos")]
+    fn select_candidate_errors_on_genuine_ambiguity() {
+        let a = ent("pool/fs", "/pool/fs", 1, 2, "abc");
+        let b = ent("pool/other", "/pool/other", 1, 2, "abc");
+
+        assert!(select_candidate(vec![&a, &b], "/pool/fs/some/file").is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "illumos")]
+    fn select_candidate_prefix_wins_across_device_groups() {
+        /*
+         * Two representatives surviving from *different* device groups but
+         * sharing a `special` must still be ranked by the same prefix rule
+         * as within a single group, not by raw mountpoint length -- a
+         * shorter mountpoint that isn't even a prefix of the queried path
+         * must lose to a longer one that is.
+         */
+        let a = ent("pool/fs", "/mnt", 1, 2, "abc");
+        let b = ent("pool/fs", "/pool/fs", 1, 3, "def");
+
+        let rep = select_candidate(vec![&a, &b], "/pool/fs/some/file").unwrap();
+        assert_eq!(rep.mountp, "/pool/fs");
+    }
+
+    #[test]
+    #[cfg(target_os = "illumos")]
+    fn select_candidate_root_dataset_ranks_as_prefix() {
+        /*
+         * A dataset mounted at "/" (e.g. the active root of a ZFS boot
+         * environment) must rank as containing every path beneath it, not
+         * lose the tie-break to an unrelated mountpoint that isn't even a
+         * prefix of the queried path.
+         */
+        let root = ent("rpool/ROOT/be", "/", 1, 2, "abc");
+        let other = ent("rpool/export", "/export", 1, 3, "def");
+
+        let rep = select_candidate(vec![&root, &other], "/home/user/file").unwrap();
+        assert_eq!(rep.mountp, "/");
+    }
+
+    #[test]
+    fn mount_options_noxattr_and_defaults() {
+        let o = MountOptions::parse("rw,noxattr");
+        assert!(o.is_readwrite());
+        assert_eq!(o.xattr(), Some(false));
+        assert_eq!(o.zone(), None);
+        assert_eq!(o.dev(), None);
+    }
+
+    #[test]
+    #[cfg(not(target_os = "illumos"))]
+    fn find_containing_mount_prefers_longest_prefix() {
+        let parent = ent("pool", "/pool", 1, 2, "abc");
+        let child = ent("pool/fs", "/pool/fs", 1, 3, "def");
+        let ents = vec![parent, child];
+
+        let found = find_containing_mount(&ents, "/pool/fs/some/file").unwrap();
+        assert_eq!(found.mountp, "/pool/fs");
+    }
+
+    #[test]
+    #[cfg(not(target_os = "illumos"))]
+    fn find_containing_mount_ignores_non_zfs() {
+        let mut other = ent("pool/fs", "/pool/fs", 1, 2, "abc");
+        other.fstype = "ext4".to_string();
+        let ents = vec![other];
+
+        assert!(find_containing_mount(&ents, "/pool/fs/some/file").is_err());
+    }
+
+    #[test]
+    #[cfg(not(target_os = "illumos"))]
+    fn find_containing_mount_matches_root_dataset() {
+        /*
+         * A ZFS boot environment's root dataset is mounted at "/" -- every
+         * path on the system, not just ones that happen to start with the
+         * literal string "//", must be recognized as living under it.
+         */
+        let root = ent("rpool/ROOT/be", "/", 1, 2, "abc");
+        let ents = vec![root];
+
+        let found = find_containing_mount(&ents, "/home/user/file").unwrap();
+        assert_eq!(found.mountp, "/");
+    }
+
+    #[test]
+    #[cfg(not(target_os = "illumos"))]
+    fn find_containing_mount_errors_on_genuine_ambiguity() {
+        let a = ent("pool/fs", "/pool/fs", 1, 2, "abc");
+        let b = ent("pool/other", "/pool/fs", 1, 3, "def");
+        let ents = vec![a, b];
+
+        assert!(find_containing_mount(&ents, "/pool/fs/some/file").is_err());
+    }
+
+    #[test]
+    fn snapshot_name_found() {
+        let p = Path::new("/pool/fs/.zfs/snapshot/daily-2026-07-30/some/file");
+        assert_eq!(
+            snapshot_name_from_components(p.components()),
+            Some("daily-2026-07-30".to_string())
+        );
+    }
+
+    #[test]
+    fn snapshot_name_requires_adjacent_zfs_snapshot() {
+        /*
+         * A literal "snapshot" directory elsewhere in the dataset, not
+         * directly preceded by ".zfs", must not be mistaken for the
+         * snapdir automount.
+         */
+        let p = Path::new("/pool/fs/archive/snapshot/not-a-snapshot-name");
+        assert_eq!(snapshot_name_from_components(p.components()), None);
+    }
+
+    #[test]
+    fn capacity_from_statvfs_blocks() {
+        let mut f: statvfs = unsafe { std::mem::zeroed() };
+        f.f_frsize = 4096;
+        f.f_blocks = 1000;
+        f.f_bfree = 400;
+        f.f_bavail = 300;
+        f.f_files = 100;
+        f.f_favail = 90;
+
+        let c = Capacity::from_statvfs(&f);
+        assert_eq!(c.total, 1000 * 4096);
+        assert_eq!(c.available, 300 * 4096);
+        assert_eq!(c.used, 600 * 4096);
+        assert_eq!(c.files, 100);
+        assert_eq!(c.files_available, 90);
+    }
+
+    #[test]
+    fn capacity_saturates_instead_of_overflowing() {
+        let mut f: statvfs = unsafe { std::mem::zeroed() };
+        f.f_frsize = u64::MAX as _;
+        f.f_blocks = u64::MAX as _;
+        f.f_bfree = 0;
+        f.f_bavail = u64::MAX as _;
+
+        let c = Capacity::from_statvfs(&f);
+        assert_eq!(c.total, u64::MAX);
+        assert_eq!(c.available, u64::MAX);
+        assert_eq!(c.used, u64::MAX);
+    }
+
+    #[test]
+    fn snapshot_name_absent() {
+        let p = Path::new("/pool/fs/some/file");
+        assert_eq!(snapshot_name_from_components(p.components()), None);
+    }
+}
@@ -0,0 +1,44 @@
+use anyhow::Result;
+
+use crate::MntTabEnt;
+
+#[cfg(target_os = "illumos")]
+mod illumos;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "freebsd")]
+mod bsd;
+
+#[cfg(target_os = "illumos")]
+pub use illumos::IllumosMountSource;
+#[cfg(target_os = "linux")]
+pub use linux::LinuxMountSource;
+#[cfg(target_os = "freebsd")]
+pub use bsd::BsdMountSource;
+
+/// A source of mount table entries.  Each supported platform has its own
+/// backend for fetching them; the classification logic in [`crate::classify_path()`]
+/// only ever deals with the resulting [`MntTabEnt`] values, so it does not
+/// need to know or care which backend produced them.
+pub trait MountSource {
+    fn mounts(&self) -> Result<Vec<MntTabEnt>>;
+}
+
+/// The `MountSource` backend appropriate for the platform this crate was
+/// built for: `getextmntent(3C)` over `/etc/mnttab` on illumos,
+/// `getmntent_r(3)` over `/proc/self/mounts` on Linux, or
+/// `getmntinfo(3)`/`getfsstat(2)` on FreeBSD.
+#[cfg(target_os = "illumos")]
+pub fn default_source() -> impl MountSource {
+    IllumosMountSource
+}
+
+#[cfg(target_os = "linux")]
+pub fn default_source() -> impl MountSource {
+    LinuxMountSource
+}
+
+#[cfg(target_os = "freebsd")]
+pub fn default_source() -> impl MountSource {
+    BsdMountSource
+}
@@ -0,0 +1,107 @@
+use std::ffi::{CStr, CString};
+
+use anyhow::{bail, Result};
+use libc::{
+    getmntinfo, major, minor, stat, statfs, MNT_NOATIME, MNT_NOEXEC, MNT_NOSUID, MNT_NOWAIT,
+    MNT_RDONLY,
+};
+
+use crate::{last_errno, MntTabEnt, MountOptions};
+
+use super::MountSource;
+
+pub struct BsdMountSource;
+
+impl MountSource for BsdMountSource {
+    fn mounts(&self) -> Result<Vec<MntTabEnt>> {
+        let mut buf: *mut statfs = std::ptr::null_mut();
+
+        let n = unsafe { getmntinfo(&mut buf, MNT_NOWAIT) };
+        if n <= 0 {
+            bail!("getmntinfo: {}", last_errno());
+        }
+
+        let ents = unsafe { std::slice::from_raw_parts(buf, n as usize) };
+
+        let mut out = Vec::new();
+
+        for e in ents {
+            /*
+             * An oddly-encoded CIFS/NFS share name or mount label can
+             * produce non-UTF-8 bytes here; lossily substitute rather than
+             * unwrap and panic the whole process over one unrelated mount.
+             */
+            let special = unsafe { CStr::from_ptr(e.f_mntfromname.as_ptr()) }
+                .to_string_lossy()
+                .into_owned();
+            let mountp = unsafe { CStr::from_ptr(e.f_mntonname.as_ptr()) }
+                .to_string_lossy()
+                .into_owned();
+            let fstype = unsafe { CStr::from_ptr(e.f_fstypename.as_ptr()) }
+                .to_string_lossy()
+                .into_owned();
+
+            /*
+             * getfsstat(2)/getmntinfo(3) hand back free/used block counts
+             * and a numeric flags bitmask, not a raw mount-option string or
+             * a device major/minor pair.  As with the Linux backend,
+             * recover major/minor with a stat(2) of the mountpoint.
+             */
+            let (major, minor) = match mountpoint_dev(&mountp) {
+                Some(d) => d,
+                None => continue,
+            };
+
+            let mntopts = mntopts_from_flags(u64::from(e.f_flags));
+            let opts = MountOptions::parse(&mntopts);
+
+            out.push(MntTabEnt {
+                special,
+                mountp,
+                fstype,
+                mntopts,
+                time: String::new(),
+                major,
+                minor,
+                opts,
+            });
+        }
+
+        Ok(out)
+    }
+}
+
+/// Translate `statfs.f_flags` into the same comma-separated flag vocabulary
+/// `MountOptions` expects out of the illumos/Linux mnttab option string, so
+/// callers don't need a separate code path to ask whether a FreeBSD mount is
+/// read-only, nosuid, and so on.
+fn mntopts_from_flags(flags: u64) -> String {
+    let mut opts = vec![if flags & MNT_RDONLY as u64 != 0 {
+        "ro"
+    } else {
+        "rw"
+    }];
+
+    if flags & MNT_NOSUID as u64 != 0 {
+        opts.push("nosuid");
+    }
+    if flags & MNT_NOEXEC as u64 != 0 {
+        opts.push("noexec");
+    }
+    if flags & MNT_NOATIME as u64 != 0 {
+        opts.push("noatime");
+    }
+
+    opts.join(",")
+}
+
+fn mountpoint_dev(mountp: &str) -> Option<(u32, u32)> {
+    let cmountp = CString::new(mountp).ok()?;
+
+    let mut st: stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::stat(cmountp.as_ptr(), &mut st) } != 0 {
+        return None;
+    }
+
+    Some((major(st.st_dev) as u32, minor(st.st_dev) as u32))
+}
@@ -0,0 +1,102 @@
+use std::ffi::CStr;
+
+use anyhow::{bail, Result};
+use libc::{c_char, c_int, c_uint, FILE};
+
+use crate::{last_errno, MntTabEnt, MountOptions};
+
+use super::MountSource;
+
+#[repr(C)]
+struct extmnttab {
+    mnt_special: *mut c_char,
+    mnt_mountp: *mut c_char,
+    mnt_fstype: *mut c_char,
+    mnt_mntopts: *mut c_char,
+    mnt_time: *mut c_char,
+    mnt_major: c_uint,
+    mnt_minor: c_uint,
+}
+
+#[allow(unused)]
+#[link(name = "c")]
+extern "C" {
+    fn resetmnttab(fp: *mut FILE);
+    fn getextmntent(fp: *mut FILE, mp: *mut extmnttab, len: c_int) -> c_int;
+}
+
+pub struct IllumosMountSource;
+
+impl MountSource for IllumosMountSource {
+    fn mounts(&self) -> Result<Vec<MntTabEnt>> {
+        let mut out = Vec::new();
+
+        let path = CStr::from_bytes_with_nul(b"/etc/mnttab\0").unwrap();
+        let mode = CStr::from_bytes_with_nul(b"r\0").unwrap();
+
+        let f = unsafe { libc::fopen(path.as_ptr(), mode.as_ptr()) };
+        if f.is_null() {
+            bail!("open mnttab: {}", last_errno());
+        }
+
+        loop {
+            let mut mp: extmnttab = unsafe { std::mem::zeroed() };
+
+            let r = unsafe {
+                getextmntent(f, &mut mp, std::mem::size_of::<extmnttab>() as i32)
+            };
+
+            if r < 0 {
+                /*
+                 * EOF.
+                 */
+                break;
+            } else if r > 0 {
+                /*
+                 * Error of some kind.
+                 */
+                unsafe { libc::fclose(f) };
+                bail!("getextmntent error {r}");
+            }
+
+            let special = unsafe { CStr::from_ptr(mp.mnt_special) }
+                .to_str()
+                .unwrap()
+                .to_string();
+            let mountp = unsafe { CStr::from_ptr(mp.mnt_mountp) }
+                .to_str()
+                .unwrap()
+                .to_string();
+            let fstype = unsafe { CStr::from_ptr(mp.mnt_fstype) }
+                .to_str()
+                .unwrap()
+                .to_string();
+            let mntopts = unsafe { CStr::from_ptr(mp.mnt_mntopts) }
+                .to_str()
+                .unwrap()
+                .to_string();
+            let time = unsafe { CStr::from_ptr(mp.mnt_time) }
+                .to_str()
+                .unwrap()
+                .to_string();
+            let major = mp.mnt_major;
+            let minor = mp.mnt_minor;
+            let opts = MountOptions::parse(&mntopts);
+
+            out.push(MntTabEnt {
+                special,
+                mountp,
+                fstype,
+                mntopts,
+                time,
+                major,
+                minor,
+                opts,
+            });
+        }
+
+        unsafe { libc::fclose(f) };
+
+        Ok(out)
+    }
+}
@@ -0,0 +1,139 @@
+use std::ffi::{CStr, CString};
+
+use anyhow::{bail, Result};
+use libc::{c_char, c_int, major, minor, mntent, stat};
+
+use crate::{last_errno, MntTabEnt, MountOptions};
+
+use super::MountSource;
+
+/*
+ * glibc doesn't expose a safe way to read back errno through the libc
+ * crate, and we need it to tell a genuinely short mtab buffer (ERANGE)
+ * apart from real end-of-file.
+ */
+#[link(name = "c")]
+extern "C" {
+    fn __errno_location() -> *mut c_int;
+}
+
+/// Stop growing the per-entry scratch buffer past this size; a mtab line
+/// that still doesn't fit here is not a buffer-sizing problem.
+const MAX_MNTENT_BUF: usize = 1 << 20;
+
+pub struct LinuxMountSource;
+
+impl MountSource for LinuxMountSource {
+    fn mounts(&self) -> Result<Vec<MntTabEnt>> {
+        let mut out = Vec::new();
+
+        let path = c"/proc/self/mounts";
+        let mode = c"r";
+
+        let f = unsafe { libc::setmntent(path.as_ptr(), mode.as_ptr()) };
+        if f.is_null() {
+            bail!("setmntent(/proc/self/mounts): {}", last_errno());
+        }
+
+        let mut buf = vec![0u8; 4096];
+
+        loop {
+            let mut ent: mntent = unsafe { std::mem::zeroed() };
+
+            unsafe { *__errno_location() = 0 };
+            let r = unsafe {
+                libc::getmntent_r(
+                    f,
+                    &mut ent,
+                    buf.as_mut_ptr() as *mut c_char,
+                    buf.len() as i32,
+                )
+            };
+
+            if r.is_null() {
+                /*
+                 * glibc returns NULL with errno == ERANGE, rather than
+                 * genuine EOF, when a line doesn't fit in our buffer (long
+                 * NFS mounts, SELinux contexts, or many bind-mount flags
+                 * can all produce lines well past 4KB).  Grow the buffer
+                 * and retry that same entry instead of silently treating
+                 * it -- and everything after it -- as though the mount
+                 * table had ended there.
+                 */
+                if unsafe { *__errno_location() } == libc::ERANGE {
+                    if buf.len() >= MAX_MNTENT_BUF {
+                        unsafe { libc::endmntent(f) };
+                        bail!("getmntent_r: mount entry exceeds {MAX_MNTENT_BUF} bytes");
+                    }
+
+                    buf.resize(buf.len() * 2, 0);
+                    continue;
+                }
+
+                /*
+                 * EOF.
+                 */
+                break;
+            }
+
+            /*
+             * A binary SELinux context blob in mnt_opts, an oddly-encoded
+             * CIFS/NFS share name, or a FUSE/overlay mount label can all
+             * produce non-UTF-8 bytes here; lossily substitute rather than
+             * unwrap and panic the whole process over one unrelated mount.
+             */
+            let special = unsafe { CStr::from_ptr(ent.mnt_fsname) }
+                .to_string_lossy()
+                .into_owned();
+            let mountp = unsafe { CStr::from_ptr(ent.mnt_dir) }
+                .to_string_lossy()
+                .into_owned();
+            let fstype = unsafe { CStr::from_ptr(ent.mnt_type) }
+                .to_string_lossy()
+                .into_owned();
+            let mntopts = unsafe { CStr::from_ptr(ent.mnt_opts) }
+                .to_string_lossy()
+                .into_owned();
+
+            /*
+             * Unlike the illumos extmnttab, glibc's struct mntent carries
+             * no device major/minor pair.  Synthesize one with a stat(2) of
+             * the mountpoint itself, which is what the classification logic
+             * compares against the device seen via stat(2)/statvfs(2) on
+             * the path under test.
+             */
+            let (major, minor) = match mountpoint_dev(&mountp) {
+                Some(d) => d,
+                None => continue,
+            };
+
+            let opts = MountOptions::parse(&mntopts);
+
+            out.push(MntTabEnt {
+                special,
+                mountp,
+                fstype,
+                mntopts,
+                time: String::new(),
+                major,
+                minor,
+                opts,
+            });
+        }
+
+        unsafe { libc::endmntent(f) };
+
+        Ok(out)
+    }
+}
+
+fn mountpoint_dev(mountp: &str) -> Option<(u32, u32)> {
+    let cmountp = CString::new(mountp).ok()?;
+
+    let mut st: stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::stat(cmountp.as_ptr(), &mut st) } != 0 {
+        return None;
+    }
+
+    Some((major(st.st_dev) as u32, minor(st.st_dev) as u32))
+}